@@ -73,28 +73,152 @@
 //! Without `DebugTag`s, the last line above would just return 1337 - a "garbage value" since `ix`
 //! stems from a different `Slab`.
 
+/// The integer type backing a `DebugTag`'s value. `u64` by default; enable the `u128-tag` feature
+/// for a 128-bit tag, at the cost of doubling `DebugTag`'s debug-build size.
+#[cfg(feature = "u128-tag")]
+type TagValue = u128;
+
+/// The integer type backing a `DebugTag`'s value. `u64` by default; enable the `u128-tag` feature
+/// for a 128-bit tag, at the cost of doubling `DebugTag`'s debug-build size.
+#[cfg(not(feature = "u128-tag"))]
+type TagValue = u64;
+
 #[cfg(debug_assertions)]
 mod checked {
+    use super::TagValue;
     use std::cell::Cell;
-    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::collections::{HashMap, HashSet};
+    use std::panic::Location;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Mutex, OnceLock};
 
-    // The increment to global every time we fetch a local tag offset. This is equal to
-    // 2**32 * (1 - 1/(golden ratio)), which ends up distributing offsets well for an arbitrary
+    // The increment to add to the global counter every time a thread seeds its local offset; the
+    // local counter itself advances by 1 on every tag allocation. This is equal to
+    // 2**WIDTH / (golden ratio), which ends up distributing offsets well for an arbitrary
     // number of local threads.
-    const INCREMENT: u32 = 1_640_531_527;
+    #[cfg(not(feature = "u128-tag"))]
+    mod golden {
+        use super::TagValue;
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        pub const INCREMENT: TagValue = 0x9E37_79B9_7F4A_7C15;
+
+        static GLOBAL: AtomicU64 = AtomicU64::new(INCREMENT);
+
+        pub fn seed() -> TagValue {
+            GLOBAL.fetch_add(INCREMENT, Ordering::SeqCst)
+        }
+    }
 
-    static GLOBAL: AtomicU32 = AtomicU32::new(INCREMENT);
+    // `u128` has no stable `std` atomic, so the 128-bit variant seeds through a `Mutex` instead.
+    #[cfg(feature = "u128-tag")]
+    mod golden {
+        use super::TagValue;
+        use std::sync::{Mutex, OnceLock};
+
+        pub const INCREMENT: TagValue = 0x9E37_79B9_7F4A_7C15_F39C_C060_5CED_C835;
+
+        static GLOBAL: OnceLock<Mutex<TagValue>> = OnceLock::new();
+
+        pub fn seed() -> TagValue {
+            let global = GLOBAL.get_or_init(|| Mutex::new(INCREMENT));
+            let mut global = global.lock().unwrap();
+            let old = *global;
+            *global = global.wrapping_add(INCREMENT);
+            old
+        }
+    }
 
     thread_local! {
-        static LOCAL: Cell<u32> = Cell::new(GLOBAL.fetch_add(INCREMENT, Ordering::SeqCst));
+        static LOCAL: Cell<TagValue> = Cell::new(golden::seed());
     }
 
-    pub fn next() -> u32 {
-        LOCAL.with(|local| {
+    // Whether every freshly generated tag value is checked for collisions against every other
+    // value generated while strict mode was enabled; values generated while it was off are not
+    // recorded. Off by default, since it requires a global lock on every allocation.
+    static STRICT: AtomicBool = AtomicBool::new(false);
+
+    static SEEN: OnceLock<Mutex<HashSet<TagValue>>> = OnceLock::new();
+
+    pub fn set_strict(enabled: bool) {
+        STRICT.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn next() -> TagValue {
+        let value = LOCAL.with(|local| {
             let old = local.get();
             local.set(old.wrapping_add(1));
             old
-        })
+        });
+
+        if STRICT.load(Ordering::Relaxed) {
+            let seen = SEEN.get_or_init(|| Mutex::new(HashSet::new()));
+            debug_assert!(
+                seen.lock().unwrap().insert(value),
+                "DebugTag value collided with a previously generated tag"
+            );
+        }
+
+        value
+    }
+
+    // The set of tag values that have been poisoned. Kept separate from `DebugTag` itself since
+    // `DebugTag` is `Copy` and poisoning must be visible to every outstanding copy of a tag, not
+    // just the one it was called on.
+    static POISONED: OnceLock<Mutex<HashSet<TagValue>>> = OnceLock::new();
+
+    fn poisoned() -> &'static Mutex<HashSet<TagValue>> {
+        POISONED.get_or_init(|| Mutex::new(HashSet::new()))
+    }
+
+    pub fn poison(tag: TagValue) {
+        poisoned().lock().unwrap().insert(tag);
+    }
+
+    pub fn is_poisoned(tag: TagValue) -> bool {
+        poisoned().lock().unwrap().contains(&tag)
+    }
+
+    // Whether freshly created tags have their creation site recorded. Enabled by default; disable
+    // on hot paths where the registry lock would be too costly.
+    static RECORDING: AtomicBool = AtomicBool::new(true);
+
+    static LOCATIONS: OnceLock<Mutex<HashMap<TagValue, &'static Location<'static>>>> = OnceLock::new();
+
+    fn locations() -> &'static Mutex<HashMap<TagValue, &'static Location<'static>>> {
+        LOCATIONS.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+
+    pub fn set_recording(enabled: bool) {
+        RECORDING.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn record_location(tag: TagValue, location: &'static Location<'static>) {
+        if RECORDING.load(Ordering::Relaxed) {
+            locations().lock().unwrap().insert(tag, location);
+        }
+    }
+
+    pub fn location_of(tag: TagValue) -> Option<&'static Location<'static>> {
+        locations().lock().unwrap().get(&tag).copied()
+    }
+
+    // Mask selecting the low 16 bits of a tag value, which `derive` uses to carry the child
+    // discriminant; everything above it carries a fingerprint of the parent tag.
+    const CHILD_MASK: TagValue = 0xFFFF;
+
+    // Spreads the parent's bits across the bits above `CHILD_MASK`, so that unrelated parents'
+    // children collide only with negligible probability.
+    fn fingerprint(parent: TagValue) -> TagValue {
+        parent.wrapping_mul(golden::INCREMENT) & !CHILD_MASK
+    }
+
+    pub fn derive(parent: TagValue, child: u16) -> TagValue {
+        fingerprint(parent) | child as TagValue
+    }
+
+    pub fn is_descendant(tag: TagValue, parent: TagValue) -> bool {
+        tag & !CHILD_MASK == fingerprint(parent)
     }
 }
 
@@ -106,21 +230,28 @@ mod checked {
 /// 
 /// This tagging is only done if `debug_assertions` is set. If `debug_assertions` is not set, then
 /// all `DebugTags` are equal. Even if `debug_assertions` is set, two `DebugTag`s that are not
-/// clones can still be equal. This is unlikely, however.
-/// 
+/// clones can still be equal. This is unlikely, however, as the tag value is a `u64` (or `u128`
+/// with the `u128-tag` feature) distributed via a golden-ratio sequence across threads; enable
+/// `set_strict` to turn that "unlikely" caveat into a checked invariant during tests.
+///
 /// Therefore, functionality should not directly depend on the equality these tags but only use them
 /// for additional sanity checks.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[non_exhaustive]
 pub struct DebugTag(
     #[cfg(debug_assertions)]
-    u32,
+    TagValue,
 );
 
 impl Default for DebugTag {
+    #[track_caller]
     fn default() -> DebugTag {
         #[cfg(debug_assertions)]
-        let tag = DebugTag(checked::next());
+        let tag = {
+            let value = checked::next();
+            checked::record_location(value, std::panic::Location::caller());
+            DebugTag(value)
+        };
 
         #[cfg(not(debug_assertions))]
         let tag = DebugTag();
@@ -131,17 +262,18 @@ impl Default for DebugTag {
 
 impl DebugTag {
     /// Creates a new `DebugTag`
+    #[track_caller]
     pub fn new() -> DebugTag {
         DebugTag::default()
     }
 
     /// Create a new tag with the specified value.
-    /// 
-    /// Prefer using `new` instead, which will generate a value. Use this only in cases where that 
+    ///
+    /// Prefer using `new` instead, which will generate a value. Use this only in cases where that
     /// is not possible, like when creating a const debug tag.
-    /// 
+    ///
     /// The tag value should be a randomly chosen constant.
-    pub const fn from(_tag: u32) -> DebugTag {
+    pub const fn from(_tag: TagValue) -> DebugTag {
         #[cfg(debug_assertions)]
         let tag = DebugTag(_tag);
 
@@ -150,11 +282,189 @@ impl DebugTag {
 
         tag
     }
+
+    /// Marks this tag as poisoned, forbidding any further use of it.
+    ///
+    /// Use this when the value a tag is attached to is freed or otherwise logically consumed, e.g.
+    /// when a slab entry is removed. Since `DebugTag` is `Copy`, poisoning is recorded in a global
+    /// registry keyed on the tag's value rather than by mutating this copy, so every outstanding
+    /// copy of the tag is poisoned as well.
+    ///
+    /// This is orthogonal to the `PartialEq` check: it catches a stale handle whose origin tag still
+    /// matches its container, but whose referent no longer exists.
+    ///
+    /// Poisoned tags are never evicted from the registry, so this permanently uses a small amount of
+    /// memory per poisoned tag for the remaining lifetime of the process; as with the rest of this
+    /// crate, this is only meant for debugging and testing, not as a mechanism to rely on at scale.
+    pub fn poison(self) {
+        #[cfg(debug_assertions)]
+        checked::poison(self.0);
+    }
+
+    /// Panics if this tag has been poisoned via `poison`.
+    pub fn assert_live(self) {
+        #[cfg(debug_assertions)]
+        assert!(!checked::is_poisoned(self.0), "DebugTag has been poisoned and must not be used");
+    }
+
+    /// Returns the source location where this tag was created, if creation-site recording was
+    /// enabled (see `set_recording`) at the time it was created.
+    ///
+    /// Like `poison`, recorded locations are never evicted; disable recording on a hot path (or
+    /// entirely, for a long-running process) if the unbounded registry growth is undesirable.
+    pub fn explain(self) -> Option<&'static std::panic::Location<'static>> {
+        #[cfg(debug_assertions)]
+        return checked::location_of(self.0);
+
+        #[cfg(not(debug_assertions))]
+        return None;
+    }
+
+    /// Derives a child tag from `self`, for checking membership in a sub-region such as a node's
+    /// edge list within a larger graph.
+    ///
+    /// The derivation is deterministic: the same parent and `child` always produce the same tag.
+    /// `child` is a small discriminant for the sub-region, e.g. a local edge-list index; it does not
+    /// need to be unique across different parents, only within one parent's children. Use
+    /// `is_descendant_of` to check whether a tag was derived from a given parent.
+    pub fn derive(self, _child: u16) -> DebugTag {
+        #[cfg(debug_assertions)]
+        let tag = DebugTag(checked::derive(self.0, _child));
+
+        #[cfg(not(debug_assertions))]
+        let tag = DebugTag();
+
+        tag
+    }
+
+    /// Returns whether `self` was produced by `parent.derive(..)` for some child discriminant.
+    pub fn is_descendant_of(self, _parent: DebugTag) -> bool {
+        #[cfg(debug_assertions)]
+        return checked::is_descendant(self.0, _parent.0);
+
+        #[cfg(not(debug_assertions))]
+        return true;
+    }
+}
+
+/// Enables or disables recording the creation site of newly created `DebugTag`s.
+///
+/// Recording is enabled by default. Disable it on hot paths where the cost of recording into the
+/// global registry is undesirable; tags created while disabled will simply have no `explain()`.
+pub fn set_recording(_enabled: bool) {
+    #[cfg(debug_assertions)]
+    checked::set_recording(_enabled);
+}
+
+/// Enables or disables strict collision checking for newly created `DebugTag`s.
+///
+/// When enabled, every freshly generated tag value is recorded into a global registry and
+/// `debug_assert`s that it does not already exist there, turning the "unlikely to collide" caveat
+/// documented on `DebugTag` into a checkable invariant. This requires a global lock on every tag
+/// creation, so it is off by default; enable it in tests rather than in hot production code paths.
+pub fn set_strict(_enabled: bool) {
+    #[cfg(debug_assertions)]
+    checked::set_strict(_enabled);
+}
+
+/// Asserts that `a` and `b` are equal, panicking with both tags' creation sites on mismatch.
+///
+/// This is like `assert_eq!(a, b, "{}", msg)`, but the panic message also explains *where* each tag
+/// came from, e.g. "tag created at src/graph.rs:42:17 used where tag from src/tree.rs:13:9 was
+/// expected" (each site is printed as file:line:column).
+pub fn assert_same(a: DebugTag, b: DebugTag, msg: &str) {
+    if a != b {
+        #[cfg(debug_assertions)]
+        panic!(
+            "{}: tag created at {} used where tag from {} was expected",
+            msg,
+            a.explain().map_or_else(|| "<unknown>".to_string(), |location| location.to_string()),
+            b.explain().map_or_else(|| "<unknown>".to_string(), |location| location.to_string()),
+        );
+
+        #[cfg(not(debug_assertions))]
+        panic!("{}", msg);
+    }
+}
+
+/// A per-slot generation counter, used together with `GenTag` to catch stale indices after a slot
+/// has been freed and reused.
+///
+/// A plain `DebugTag` cannot catch this on its own: a container's origin tag does not change when
+/// one of its slots is recycled, so a stale index into a freed-and-reused slot still passes the
+/// origin check. Bump the slot's `Generation` on every free, and mint a fresh `GenTag` with the new
+/// generation whenever the slot is reused.
+#[derive(Debug, Default)]
+pub struct Generation(#[cfg(debug_assertions)] std::cell::Cell<u32>);
+
+impl Generation {
+    /// Creates a new generation counter, starting at generation `0`.
+    pub fn new() -> Generation {
+        Generation::default()
+    }
+
+    /// Bumps the generation, invalidating any `GenTag` minted against the previous value.
+    pub fn bump(&self) {
+        #[cfg(debug_assertions)]
+        self.0.set(self.0.get().wrapping_add(1));
+    }
+
+    /// Bumps the generation and returns the new value. Convenient when reusing a slot: bump to
+    /// invalidate old handles, then mint new ones from the returned value.
+    pub fn next_generation(&self) -> u32 {
+        self.bump();
+        self.current()
+    }
+
+    /// Returns the current generation value.
+    pub fn current(&self) -> u32 {
+        #[cfg(debug_assertions)]
+        return self.0.get();
+
+        #[cfg(not(debug_assertions))]
+        return 0;
+    }
+}
+
+/// A `DebugTag` bundled with a generation counter, so that a checked access can assert both that a
+/// handle stems from the right container *and* that it stems from the slot's current generation.
+///
+/// Like `DebugTag`, this tagging is only done if `debug_assertions` is set; two `GenTag`s are always
+/// equal if `debug_assertions` is not set.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub struct GenTag {
+    origin: DebugTag,
+    #[cfg(debug_assertions)]
+    generation: u32,
+}
+
+impl GenTag {
+    /// Creates a tag for the current generation of `generation`, tagged with the given origin.
+    pub fn new(origin: DebugTag, generation: &Generation) -> GenTag {
+        GenTag::with_generation(origin, generation.current())
+    }
+
+    /// Creates a tag with an explicit generation value.
+    ///
+    /// Prefer `new`, which reads the generation from a `Generation` counter. Use this only in cases
+    /// where that is not possible, like when creating a const tag.
+    pub const fn with_generation(origin: DebugTag, _generation: u32) -> GenTag {
+        #[cfg(debug_assertions)]
+        let tag = GenTag { origin, generation: _generation };
+
+        #[cfg(not(debug_assertions))]
+        let tag = GenTag { origin };
+
+        tag
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::DebugTag;
+    use super::{DebugTag, GenTag, Generation};
+    #[cfg(debug_assertions)]
+    use super::{assert_same, set_strict};
 
     #[test]
     #[cfg(debug_assertions)]
@@ -176,4 +486,122 @@ mod tests {
         let a = DebugTag::new();
         assert!(a == a);
     }
+
+    #[test]
+    fn assert_live_passes_until_poisoned() {
+        let a = DebugTag::new();
+        a.assert_live();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn assert_live_panics_after_poison() {
+        let a = DebugTag::new();
+        a.poison();
+        a.assert_live();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn poison_is_visible_to_copies() {
+        let a = DebugTag::new();
+        let b = a;
+        a.poison();
+        assert!(std::panic::catch_unwind(|| b.assert_live()).is_err());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn explain_reports_creation_site() {
+        let a = DebugTag::new();
+        let location = a.explain().expect("recording is enabled by default");
+        assert!(location.file().ends_with("lib.rs"));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn assert_same_panics_with_creation_sites() {
+        let a = DebugTag::new();
+        let b = DebugTag::new();
+        let result = std::panic::catch_unwind(|| assert_same(a, b, "mismatch"));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("mismatch"));
+        assert!(message.contains("tag created at"));
+    }
+
+    #[test]
+    fn gen_tag_equal_for_same_generation() {
+        let origin = DebugTag::new();
+        let generation = Generation::new();
+        let a = GenTag::new(origin, &generation);
+        let b = GenTag::new(origin, &generation);
+        assert!(a == b);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn gen_tag_not_equal_after_bump() {
+        let origin = DebugTag::new();
+        let generation = Generation::new();
+        let a = GenTag::new(origin, &generation);
+        generation.bump();
+        let b = GenTag::new(origin, &generation);
+        assert!(a != b);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn next_generation_bumps_and_returns_new_value() {
+        let generation = Generation::new();
+        assert_eq!(generation.current(), 0);
+        assert_eq!(generation.next_generation(), 1);
+        assert_eq!(generation.current(), 1);
+    }
+
+    #[test]
+    fn derive_is_deterministic() {
+        let parent = DebugTag::new();
+        assert!(parent.derive(7) == parent.derive(7));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn derive_is_descendant_of_its_parent() {
+        let parent = DebugTag::new();
+        let child = parent.derive(7);
+        assert!(child.is_descendant_of(parent));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn derive_is_not_descendant_of_unrelated_tag() {
+        let parent = DebugTag::new();
+        let other = DebugTag::new();
+        let child = parent.derive(7);
+        assert!(!child.is_descendant_of(other));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn derive_different_children_differ() {
+        let parent = DebugTag::new();
+        assert!(parent.derive(1) != parent.derive(2));
+    }
+
+    // `set_strict` is process-global, like the tag counter itself, so this also exercises any tags
+    // created by other tests running concurrently; that's fine, since it only ever asserts on an
+    // actual collision.
+    #[test]
+    #[cfg(debug_assertions)]
+    fn strict_mode_does_not_flag_distinct_tags() {
+        set_strict(true);
+        let tags: Vec<DebugTag> = (0..64).map(|_| DebugTag::new()).collect();
+        set_strict(false);
+        for (i, a) in tags.iter().enumerate() {
+            for b in &tags[i + 1..] {
+                assert!(a != b);
+            }
+        }
+    }
 }
\ No newline at end of file